@@ -0,0 +1,171 @@
+use std::env;
+use std::process::Command;
+
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, ETAG, IF_NONE_MATCH, USER_AGENT};
+use reqwest::{Client, StatusCode};
+use serde::Deserializer;
+
+use crate::date::LastUpdated;
+use crate::entry::{DeserializeUserRepos, Entry};
+
+const API_BASE: &str = "https://api.github.com";
+
+/// A thin client over the GitHub REST API, used to list the repositories belonging to a user or
+/// organization without depending on the `gh` binary.
+pub struct GitHubClient {
+    client: Client,
+    token: String,
+}
+
+impl GitHubClient {
+    /// Construct a client, reading a token from `GH_TOKEN`, `GITHUB_TOKEN`, or failing that,
+    /// `gh auth token`.
+    pub fn new() -> Result<Self> {
+        let token = read_token()?;
+
+        let mut auth_value = HeaderValue::from_str(&format!("Bearer {token}"))?;
+        auth_value.set_sensitive(true);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, auth_value);
+        headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github+json"));
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_static(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"))),
+        );
+
+        let client = Client::builder().default_headers(headers).build()?;
+
+        Ok(Self { client, token })
+    }
+
+    /// The token this client authenticates with, so callers which also need to authenticate
+    /// (e.g. libgit2 clones/fetches) don't have to look it up a second time.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// List the repositories belonging to `account`, following `Link: rel="next"` pagination
+    /// until `max` repositories have been seen or the pages run out, returning the ones which
+    /// are outdated.
+    ///
+    /// The first page is sent with `If-None-Match` set to the account's cached `ETag`; a `304
+    /// Not Modified` response short-circuits the whole listing, since nothing in it can have
+    /// changed since the last successful fetch.
+    pub async fn list_repos(
+        &self,
+        account: &str,
+        max: usize,
+        last_updated: &LastUpdated,
+        on_push_only: bool,
+    ) -> Result<Vec<Entry>> {
+        let mut to_update = Vec::new();
+        let mut url = format!("{API_BASE}/users/{account}/repos?per_page=100&sort=updated");
+        let mut seen = 0;
+        let mut first_page = true;
+
+        loop {
+            let etag = if first_page {
+                last_updated.get_etag(account)?
+            } else {
+                None
+            };
+
+            let build_request = |url: &str| {
+                let mut request = self.client.get(url);
+                if let Some(etag) = &etag {
+                    request = request.header(IF_NONE_MATCH, etag.clone());
+                }
+                request
+            };
+
+            let response = build_request(&url).send().await?;
+
+            let response = if response.status() == StatusCode::NOT_FOUND {
+                // `account` may be an organization rather than a user.
+                let org_url = url.replacen("/users/", "/orgs/", 1);
+                build_request(&org_url).send().await?
+            } else {
+                response
+            };
+
+            if first_page && response.status() == StatusCode::NOT_MODIFIED {
+                return Ok(to_update);
+            }
+
+            if !response.status().is_success() {
+                return Err(eyre!(
+                    "failed to list repositories for `{account}`: {}",
+                    response.status()
+                ));
+            }
+
+            if first_page {
+                if let Some(etag) = response.headers().get(ETAG).and_then(|v| v.to_str().ok()) {
+                    last_updated.set_etag(account, etag)?;
+                }
+            }
+
+            let next = next_link(response.headers());
+
+            let body = response.bytes().await?;
+            seen += count_array_elements(&body)?;
+
+            let mut json_de = serde_json::Deserializer::from_slice(&body);
+            json_de.deserialize_seq(DeserializeUserRepos::new(last_updated, &mut to_update, on_push_only))?;
+
+            first_page = false;
+
+            match next {
+                Some(next_url) if seen < max => url = next_url,
+                _ => break,
+            }
+        }
+
+        Ok(to_update)
+    }
+}
+
+/// Count the elements in a top-level JSON array without fully deserializing it, so pagination
+/// can stop once `max` repositories have been seen.
+fn count_array_elements(body: &[u8]) -> Result<usize> {
+    let value: Vec<serde::de::IgnoredAny> = serde_json::from_slice(body)?;
+    Ok(value.len())
+}
+
+/// Parse the `rel="next"` target out of a GitHub `Link` response header.
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+
+    link.split(',').find_map(|part| {
+        let mut segments = part.split(';');
+        let url = segments.next()?.trim();
+        let url = url.strip_prefix('<')?.strip_suffix('>')?;
+
+        segments
+            .any(|seg| seg.trim() == "rel=\"next\"")
+            .then(|| url.to_string())
+    })
+}
+
+/// Read a GitHub token from the environment, falling back to the `gh` CLI's cached credentials.
+fn read_token() -> Result<String> {
+    if let Ok(token) = env::var("GH_TOKEN") {
+        return Ok(token);
+    }
+    if let Ok(token) = env::var("GITHUB_TOKEN") {
+        return Ok(token);
+    }
+
+    let output = Command::new("gh")
+        .args(["auth", "token"])
+        .output()
+        .wrap_err("no GH_TOKEN or GITHUB_TOKEN set, and failed to run `gh auth token`")?;
+
+    if !output.status.success() {
+        return Err(eyre!("`gh auth token` did not return a token"));
+    }
+
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
@@ -1,46 +1,232 @@
-use std::collections::HashMap;
-
-use std::fs::File;
-use std::io::{BufReader, BufWriter};
 use std::path::Path;
 
-use chrono::{DateTime, FixedOffset};
-use serde::{Deserialize, Serialize};
-
+use chrono::{DateTime, FixedOffset, Local};
 use color_eyre::eyre::Result;
+use rusqlite::{params, Connection, OptionalExtension};
 
 use crate::entry::Entry;
 
-/// A record of the previous updates.
-#[derive(Debug, Default, Deserialize, Serialize)]
-pub struct LastUpdated(HashMap<String, DateTime<FixedOffset>>);
+/// A durable, queryable record of every repository this tool has attempted to back up.
+pub struct LastUpdated {
+    conn: Connection,
+}
+
+/// A single row of the backup catalog, as printed by `--list`.
+#[derive(Debug)]
+pub struct CatalogEntry {
+    pub repo: String,
+    pub last_success: Option<DateTime<FixedOffset>>,
+    pub last_attempt: Option<DateTime<FixedOffset>>,
+    pub last_status: Option<String>,
+    pub size: Option<u64>,
+    pub default_branch: Option<String>,
+    pub description: Option<String>,
+}
 
 impl LastUpdated {
-    /// Read the update record from a file.
-    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        match File::open(path) {
-            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
-            Err(_) => Ok(Self::default()),
-        }
+    /// Open (creating if necessary) the SQLite catalog at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Open an in-memory catalog, for tests.
+    #[cfg(test)]
+    fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS repos (
+                name_with_owner TEXT PRIMARY KEY,
+                last_success    TEXT,
+                last_attempt    TEXT,
+                last_status     TEXT,
+                size            INTEGER,
+                default_branch  TEXT,
+                description     TEXT,
+                last_updated_at TEXT,
+                last_pushed_at  TEXT
+            );
+            CREATE TABLE IF NOT EXISTS account_etags (
+                account TEXT PRIMARY KEY,
+                etag    TEXT NOT NULL
+            );",
+        )?;
+        Ok(Self { conn })
     }
 
-    /// Write the update record to a file.
-    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
+    /// Look up the `ETag` stored for an account's repository listing, if any.
+    pub fn get_etag(&self, account: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT etag FROM account_etags WHERE account = ?1",
+                params![account],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
 
-        Ok(serde_json::to_writer(writer, &self)?)
+    /// Store the `ETag` returned for an account's repository listing.
+    pub fn set_etag(&self, account: &str, etag: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO account_etags (account, etag) VALUES (?1, ?2)
+             ON CONFLICT(account) DO UPDATE SET etag = excluded.etag",
+            params![account, etag],
+        )?;
+        Ok(())
     }
 
     /// Check whether or not an entry is outdated.
-    pub fn is_outdated(&self, entry: &Entry) -> bool {
-        match self.0.get(&entry.repo) {
-            Some(dt) => &entry.last_updated >= dt,
+    ///
+    /// By default this compares `updated_at`, which GitHub also bumps on purely cosmetic
+    /// changes (stars, description edits, topics). When `on_push_only` is set, `pushed_at` is
+    /// used instead, so a repository is only considered outdated once its branches actually
+    /// changed. Both timestamps are always recorded, so switching modes between runs stays
+    /// correct.
+    pub fn is_outdated(&self, entry: &Entry, on_push_only: bool) -> Result<bool> {
+        let column = if on_push_only {
+            "last_pushed_at"
+        } else {
+            "last_updated_at"
+        };
+
+        let stored: Option<String> = self
+            .conn
+            .query_row(
+                &format!("SELECT {column} FROM repos WHERE name_with_owner = ?1"),
+                params![entry.repo],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+
+        let current = if on_push_only {
+            entry.last_pushed
+        } else {
+            entry.last_updated
+        };
+
+        Ok(match stored {
+            Some(raw) => current > DateTime::parse_from_rfc3339(&raw)?,
             None => true,
+        })
+    }
+
+    /// Record a successful update, storing the entry's metadata alongside the current wall-clock
+    /// time as the fetch time.
+    pub fn record_success(&self, entry: &Entry) -> Result<()> {
+        let now: DateTime<FixedOffset> = Local::now().into();
+        self.conn.execute(
+            "INSERT INTO repos (name_with_owner, last_success, last_attempt, last_status, size, default_branch, description, last_updated_at, last_pushed_at)
+             VALUES (?1, ?2, ?2, 'ok', ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(name_with_owner) DO UPDATE SET
+                last_success    = excluded.last_success,
+                last_attempt    = excluded.last_attempt,
+                last_status     = excluded.last_status,
+                size            = excluded.size,
+                default_branch  = excluded.default_branch,
+                description     = excluded.description,
+                last_updated_at = excluded.last_updated_at,
+                last_pushed_at  = excluded.last_pushed_at",
+            params![
+                entry.repo,
+                now.to_rfc3339(),
+                entry.size,
+                entry.default_branch,
+                entry.description,
+                entry.last_updated.to_rfc3339(),
+                entry.last_pushed.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record a failed update attempt, keeping the previous last-success time and metadata.
+    pub fn record_failure(&self, repo: &str, error: &str) -> Result<()> {
+        let now: DateTime<FixedOffset> = Local::now().into();
+        self.conn.execute(
+            "INSERT INTO repos (name_with_owner, last_attempt, last_status)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(name_with_owner) DO UPDATE SET
+                last_attempt = excluded.last_attempt,
+                last_status  = excluded.last_status",
+            params![repo, now.to_rfc3339(), error],
+        )?;
+        Ok(())
+    }
+
+    /// List every repository in the catalog, ordered by name.
+    pub fn list(&self) -> Result<Vec<CatalogEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name_with_owner, last_success, last_attempt, last_status, size, default_branch, description
+             FROM repos ORDER BY name_with_owner",
+        )?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(CatalogEntry {
+                    repo: row.get(0)?,
+                    last_success: parse_opt_rfc3339(row.get(1)?),
+                    last_attempt: parse_opt_rfc3339(row.get(2)?),
+                    last_status: row.get(3)?,
+                    size: row.get(4)?,
+                    default_branch: row.get(5)?,
+                    description: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(rows)
+    }
+}
+
+fn parse_opt_rfc3339(raw: Option<String>) -> Option<DateTime<FixedOffset>> {
+    raw.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(repo: &str, last_updated: &str, last_pushed: &str) -> Entry {
+        Entry {
+            repo: repo.to_string(),
+            last_updated: DateTime::parse_from_rfc3339(last_updated).unwrap(),
+            last_pushed: DateTime::parse_from_rfc3339(last_pushed).unwrap(),
+            default_branch: "main".to_string(),
+            description: None,
+            size: 0,
         }
     }
 
-    pub fn update(&mut self, repo: String, at: DateTime<FixedOffset>) {
-        self.0.insert(repo, at);
+    #[test]
+    fn unchanged_entry_is_not_outdated() {
+        let store = LastUpdated::open_in_memory().unwrap();
+        let current = entry("owner/repo", "2024-01-01T00:00:00Z", "2024-01-01T00:00:00Z");
+        store.record_success(&current).unwrap();
+
+        assert!(!store.is_outdated(&current, false).unwrap());
+        assert!(!store.is_outdated(&current, true).unwrap());
+    }
+
+    #[test]
+    fn newer_timestamp_is_outdated() {
+        let store = LastUpdated::open_in_memory().unwrap();
+        let first = entry("owner/repo", "2024-01-01T00:00:00Z", "2024-01-01T00:00:00Z");
+        store.record_success(&first).unwrap();
+
+        let updated = entry("owner/repo", "2024-02-01T00:00:00Z", "2024-01-01T00:00:00Z");
+        assert!(store.is_outdated(&updated, false).unwrap());
+        assert!(!store.is_outdated(&updated, true).unwrap());
+    }
+
+    #[test]
+    fn unseen_entry_is_outdated() {
+        let store = LastUpdated::open_in_memory().unwrap();
+        let fresh = entry("owner/repo", "2024-01-01T00:00:00Z", "2024-01-01T00:00:00Z");
+
+        assert!(store.is_outdated(&fresh, false).unwrap());
     }
 }
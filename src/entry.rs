@@ -9,27 +9,35 @@ use serde::{
 use crate::LastUpdated;
 
 /// A single repository entry returned by the GitHub API.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Entry {
-    #[serde(rename = "nameWithOwner")]
+    #[serde(rename = "full_name")]
     pub repo: String,
-    #[serde(rename = "updatedAt")]
+    #[serde(rename = "updated_at")]
     pub last_updated: DateTime<FixedOffset>,
+    #[serde(rename = "pushed_at")]
+    pub last_pushed: DateTime<FixedOffset>,
+    pub default_branch: String,
+    pub description: Option<String>,
+    pub size: u64,
 }
 
 /// A deserializer for the list of repositories returned by the GitHub API.
 pub struct DeserializeUserRepos<'a> {
     last_updated: &'a LastUpdated,
     entries: &'a mut Vec<Entry>,
+    on_push_only: bool,
 }
 
 impl<'a> DeserializeUserRepos<'a> {
-    /// Initialize the deserializer to deserialize all entries which are updated after a certain
-    /// date, and append to `entries`.
-    pub fn new(last_updated: &'a LastUpdated, entries: &'a mut Vec<Entry>) -> Self {
+    /// Initialize the deserializer to deserialize all entries which are outdated and append
+    /// them to `entries`. When `on_push_only` is set, an entry is outdated only if its last
+    /// push is newer than the last recorded one, rather than any metadata update.
+    pub fn new(last_updated: &'a LastUpdated, entries: &'a mut Vec<Entry>, on_push_only: bool) -> Self {
         Self {
             last_updated,
             entries,
+            on_push_only,
         }
     }
 }
@@ -38,7 +46,7 @@ impl<'a, 'de> Visitor<'de> for DeserializeUserRepos<'a> {
     type Value = ();
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("json returned by `gh repo ls ... --json nameWithOwner --json updatedAt`")
+        f.write_str("a JSON array of repositories as returned by the GitHub REST API")
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -46,7 +54,12 @@ impl<'a, 'de> Visitor<'de> for DeserializeUserRepos<'a> {
         A: SeqAccess<'de>,
     {
         while let Some(entry) = seq.next_element::<Entry>()? {
-            if self.last_updated.is_outdated(&entry) {
+            let is_outdated = self
+                .last_updated
+                .is_outdated(&entry, self.on_push_only)
+                .map_err(serde::de::Error::custom)?;
+
+            if is_outdated {
                 self.entries.push(entry)
             }
         }
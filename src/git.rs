@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use color_eyre::eyre::{eyre, Result};
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{Cred, FetchOptions, RemoteCallbacks, Repository};
+
+/// The outcome of updating a single repository.
+pub enum UpdateOutcome {
+    /// The repository did not exist locally and was freshly cloned.
+    Cloned,
+    /// The repository was fetched, but HEAD already pointed at the latest commit.
+    UpToDate,
+    /// The repository was fetched and fast-forwarded to a new commit.
+    Fetched,
+}
+
+/// Clone or update `repo` (a GitHub `owner/name` slug) inside `backup_path` using libgit2,
+/// authenticating over HTTPS with `token`.
+pub fn update_repo(repo: &str, backup_path: &Path, token: &str) -> Result<UpdateOutcome> {
+    let path = backup_path.join(repo);
+
+    match Repository::open(&path) {
+        Ok(repository) => fetch_and_fast_forward(&repository, token),
+        Err(_) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let url = format!("https://github.com/{repo}.git");
+
+            let mut fetch_options = FetchOptions::new();
+            fetch_options.remote_callbacks(remote_callbacks(token.to_string()));
+
+            RepoBuilder::new().fetch_options(fetch_options).clone(&url, &path)?;
+            Ok(UpdateOutcome::Cloned)
+        }
+    }
+}
+
+/// Build the credential callback used for both clones and fetches: GitHub accepts any
+/// username over HTTPS as long as the password is a valid token.
+fn remote_callbacks(token: String) -> RemoteCallbacks<'static> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |_url, _username_from_url, _allowed_types| {
+        Cred::userpass_plaintext("x-access-token", &token)
+    });
+    callbacks
+}
+
+/// Fetch `origin` and fast-forward the current branch's working tree to match.
+fn fetch_and_fast_forward(repository: &Repository, token: &str) -> Result<UpdateOutcome> {
+    let head = repository.head()?;
+    let branch_name = head
+        .shorthand()
+        .ok_or_else(|| eyre!("HEAD is not a valid UTF-8 branch name"))?
+        .to_string();
+
+    let mut remote = repository.find_remote("origin")?;
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(remote_callbacks(token.to_string()));
+    remote.fetch(&[branch_name.as_str()], Some(&mut fetch_options), None)?;
+
+    let remote_ref_name = format!("refs/remotes/origin/{branch_name}");
+    let remote_oid = repository.refname_to_id(&remote_ref_name)?;
+    let remote_commit = repository.find_annotated_commit(remote_oid)?;
+
+    let (analysis, _) = repository.merge_analysis(&[&remote_commit])?;
+
+    if analysis.is_up_to_date() {
+        return Ok(UpdateOutcome::UpToDate);
+    }
+    if !analysis.is_fast_forward() {
+        return Err(eyre!(
+            "refusing to update `{branch_name}`: local history has diverged from origin"
+        ));
+    }
+
+    let local_ref_name = format!("refs/heads/{branch_name}");
+    let mut local_ref = repository.find_reference(&local_ref_name)?;
+    local_ref.set_target(remote_oid, "fast-forward update")?;
+    repository.set_head(&local_ref_name)?;
+    repository.checkout_head(Some(CheckoutBuilder::new().force()))?;
+
+    Ok(UpdateOutcome::Fetched)
+}
@@ -1,19 +1,23 @@
+pub mod client;
 pub mod date;
 pub mod entry;
+pub mod git;
+pub mod notifier;
 
 use std::fs;
-use std::path::Path;
-use std::process::{ExitStatus, Stdio};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use chrono::{DateTime, FixedOffset, Local};
+use chrono::{DateTime, FixedOffset};
 use clap::Parser;
 use color_eyre::eyre::Result;
-use serde::Deserializer;
-use tokio::process::Command;
+use futures::future::try_join_all;
+use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 
+use client::GitHubClient;
 use date::LastUpdated;
-use entry::DeserializeUserRepos;
+use notifier::{Notifier, RepoOutcome, RunSummary};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -26,10 +30,40 @@ struct Cli {
     #[arg(short, long, value_name = "NUM", default_value_t = 20)]
     limit: usize,
 
+    /// The maximum number of repositories to fetch concurrently.
+    #[arg(short, long, value_name = "NUM", default_value_t = 4, value_parser = parse_nonzero_jobs)]
+    jobs: usize,
+
+    /// Print the backup catalog instead of running an update.
+    #[arg(long)]
+    list: bool,
+
+    /// Write a JSON run summary to this file in addition to the default stderr report.
+    #[arg(long, value_name = "PATH")]
+    notify_json: Option<PathBuf>,
+
+    /// POST a JSON run summary to this URL in addition to the default stderr report.
+    #[arg(long, value_name = "URL")]
+    notify_webhook: Option<String>,
+
+    /// Only consider a repository outdated when it has been pushed to, ignoring cosmetic
+    /// metadata updates (stars, description, topics).
+    #[arg(long)]
+    on_push_only: bool,
+
     /// The list of users to backup.
     users: Vec<String>,
 }
 
+/// Parse `--jobs`, rejecting 0: a zero-permit semaphore would never grant and hang the run.
+fn parse_nonzero_jobs(raw: &str) -> Result<usize, String> {
+    match raw.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(value) => Ok(value),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
 pub struct BackupFile<'a> {
     path: &'a str,
 }
@@ -51,98 +85,112 @@ impl<'a> BackupFile<'a> {
     }
 }
 
-/// Update the repository, recording the update time and whether or not the update was successful.
-pub async fn git_update(
-    repo: String,
-    backup_path: &'static Path,
-) -> Result<(String, DateTime<FixedOffset>, ExitStatus), std::io::Error> {
-    let execute_time = Local::now().into();
-
-    let status = Command::new("git")
-        .args(["-C", &repo, "pull"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .current_dir(&backup_path)
-        .status()
-        .await?;
-
-    let status = if !status.success() {
-        Command::new("gh")
-            .args(["repo", "clone", &repo, &repo])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .current_dir(&backup_path)
-            .status()
-            .await?
-    } else {
-        status
-    };
-
-    Ok((repo, execute_time, status))
-}
-
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     color_eyre::install()?;
 
-    let Cli { limit, max, users } = Cli::parse();
-    let max_string = max.to_string();
+    let Cli {
+        limit,
+        max,
+        jobs,
+        list,
+        notify_json,
+        notify_webhook,
+        on_push_only,
+        users,
+    } = Cli::parse();
 
     let xdg_dirs = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"))?;
-    let last_updated_path = xdg_dirs.place_data_file("last_updated.json")?;
+    let catalog_path = xdg_dirs.place_data_file("catalog.db")?;
     let backup_path: &'static Path = Box::leak(Box::new(xdg_dirs.create_data_directory("backup")?));
 
-    // read last updated
-    let mut last_updated = LastUpdated::read_from_file(&last_updated_path)?;
-
-    // initialize futures
-    let mut entry_set = JoinSet::new();
-    for user in users {
-        let output = Command::new("gh")
-            .args([
-                "repo",
-                "ls",
-                &user,
-                "--limit",
-                &max_string,
-                "--json",
-                "nameWithOwner",
-                "--json",
-                "updatedAt",
-            ])
-            .current_dir(&backup_path)
-            .output();
-
-        entry_set.spawn(output);
+    let last_updated = LastUpdated::open(&catalog_path)?;
+
+    if list {
+        print_catalog(&last_updated)?;
+        return Ok(());
     }
 
-    // join futures to get all entries which require updating
-    let mut to_update = Vec::new();
-    while let Some(output) = entry_set.join_next().await {
-        let output = output??.stdout;
+    let github = GitHubClient::new()?;
 
-        let mut json_de = serde_json::Deserializer::from_slice(&output);
-        json_de.deserialize_seq(DeserializeUserRepos::new(&last_updated, &mut to_update))?;
-    }
+    // list every account's repositories concurrently, collecting the ones which require updating
+    let mut to_update: Vec<_> = try_join_all(
+        users
+            .iter()
+            .map(|user| github.list_repos(user, max, &last_updated, on_push_only)),
+    )
+    .await?
+    .into_iter()
+    .flatten()
+    .collect();
 
     to_update.truncate(limit);
 
-    // update the corresponding entries
+    // update the corresponding entries, bounding concurrency to `jobs` fetches at a time
+    let token = github.token().to_string();
+    let semaphore = Arc::new(Semaphore::new(jobs));
     let mut update_set = JoinSet::new();
+    let total = to_update.len();
     for entry in to_update.drain(..) {
-        let cmd = git_update(entry.repo, backup_path);
-        update_set.spawn(cmd);
+        let repo_name = entry.repo.clone();
+        let token = token.clone();
+        let permit = semaphore.clone().acquire_owned().await?;
+        update_set.spawn_blocking(move || {
+            let _permit = permit;
+            (entry, git::update_repo(&repo_name, backup_path, &token))
+        });
     }
 
     // record the corresponding updates
+    let mut progress = pbr::ProgressBar::new(total as u64);
+    progress.message("backing up: ");
+
+    let mut summary = RunSummary::default();
     while let Some(res) = update_set.join_next().await {
-        let (repo, execute_time, status) = res??;
-        if status.success() {
-            last_updated.update(repo, execute_time);
-        }
+        let (entry, result) = res?;
+        let repo = entry.repo.clone();
+
+        let outcome = match result {
+            Ok(git::UpdateOutcome::UpToDate) => {
+                last_updated.record_success(&entry)?;
+                RepoOutcome::Skipped
+            }
+            Ok(_) => {
+                last_updated.record_success(&entry)?;
+                RepoOutcome::Updated
+            }
+            Err(err) => {
+                last_updated.record_failure(&repo, &err.to_string())?;
+                RepoOutcome::Failed(err.to_string())
+            }
+        };
+
+        progress.message(&format!("{repo} "));
+        progress.inc();
+        summary.record(repo, outcome);
     }
+    progress.finish();
+
+    let notifier = Notifier::new(notify_json, notify_webhook);
+    notifier.notify(&summary).await;
 
-    last_updated.write_to_file(last_updated_path)?;
+    Ok(())
+}
+
+/// Print the backup catalog to stdout, one repository per line.
+fn print_catalog(last_updated: &LastUpdated) -> Result<()> {
+    for entry in last_updated.list()? {
+        println!(
+            "{:<40} {:<6} {:<30} {}",
+            entry.repo,
+            entry.last_status.as_deref().unwrap_or("-"),
+            entry
+                .last_success
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_else(|| "never".to_string()),
+            entry.description.as_deref().unwrap_or(""),
+        );
+    }
 
     Ok(())
 }
@@ -0,0 +1,121 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use color_eyre::eyre::Result;
+use serde::Serialize;
+
+/// The outcome of attempting to update a single repository.
+#[derive(Debug, Clone)]
+pub enum RepoOutcome {
+    /// The repository was cloned or fetched new commits.
+    Updated,
+    /// The repository was fetched but already had the latest commit.
+    Skipped,
+    /// The update failed with the given error.
+    Failed(String),
+}
+
+/// The aggregated outcome of a single run, reported through a [`Notifier`] once the run
+/// completes.
+#[derive(Debug, Default, Serialize)]
+pub struct RunSummary {
+    pub updated: Vec<String>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl RunSummary {
+    /// Record the outcome for a single repository.
+    pub fn record(&mut self, repo: String, outcome: RepoOutcome) {
+        match outcome {
+            RepoOutcome::Updated => self.updated.push(repo),
+            RepoOutcome::Skipped => self.skipped.push(repo),
+            RepoOutcome::Failed(error) => self.failed.push((repo, error)),
+        }
+    }
+}
+
+/// A destination a [`RunSummary`] can be reported to.
+trait Sink {
+    fn report(&self, summary: &RunSummary) -> Result<()>;
+}
+
+/// Print a short human-readable summary table to stderr. Always active.
+struct StderrSink;
+
+impl Sink for StderrSink {
+    fn report(&self, summary: &RunSummary) -> Result<()> {
+        eprintln!(
+            "backup run: {} updated, {} skipped, {} failed",
+            summary.updated.len(),
+            summary.skipped.len(),
+            summary.failed.len(),
+        );
+        for (repo, error) in &summary.failed {
+            eprintln!("  FAILED {repo}: {error}");
+        }
+        Ok(())
+    }
+}
+
+/// Write the summary as JSON to a file.
+struct JsonFileSink {
+    path: PathBuf,
+}
+
+impl Sink for JsonFileSink {
+    fn report(&self, summary: &RunSummary) -> Result<()> {
+        let file = File::create(&self.path)?;
+        Ok(serde_json::to_writer_pretty(file, summary)?)
+    }
+}
+
+/// Dispatches a [`RunSummary`] (aggregated by [`RunSummary::record`]) to every sink selected
+/// on the command line.
+pub struct Notifier {
+    json_path: Option<PathBuf>,
+    webhook_url: Option<String>,
+}
+
+impl Notifier {
+    /// Construct a notifier that always reports to stderr, and additionally to a JSON file
+    /// and/or webhook if configured.
+    pub fn new(json_path: Option<PathBuf>, webhook_url: Option<String>) -> Self {
+        Self {
+            json_path,
+            webhook_url,
+        }
+    }
+
+    /// Dispatch `summary` to every configured sink. A failing optional sink (JSON file,
+    /// webhook) is logged and does not fail the run — a broken notification endpoint
+    /// shouldn't turn a successful backup into a non-zero exit on an unattended cron.
+    pub async fn notify(&self, summary: &RunSummary) {
+        if let Err(err) = StderrSink.report(summary) {
+            eprintln!("notify: stderr report failed: {err}");
+        }
+
+        if let Some(path) = &self.json_path {
+            if let Err(err) = (JsonFileSink { path: path.clone() }).report(summary) {
+                eprintln!("notify: json file report failed: {err}");
+            }
+        }
+
+        if let Some(url) = &self.webhook_url {
+            let result: Result<()> = async {
+                reqwest::Client::new()
+                    .post(url)
+                    .json(summary)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(())
+            }
+            .await;
+
+            if let Err(err) = result {
+                eprintln!("notify: webhook failed: {err}");
+            }
+        }
+    }
+}